@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::modes::GameMode;
+use crate::player::Player;
+use crate::protocol::RosterEntry;
+use crate::transport::PlayerTransport;
+
+/// Outcome of attempting to add or reconnect a player to a room.
+#[derive(Debug, PartialEq, Eq)]
+pub enum JoinResult {
+    Joined,
+    NameTaken,
+    RoomFull,
+}
+
+#[derive(Debug)]
+pub struct GameState {
+    pub current_round: u32,
+    pub mode: Box<dyn GameMode>,
+    pub players: HashMap<String, Player>,
+    pub active_connections: HashMap<String, String>, // player_id -> player_name for active connections
+    pub round_active: bool,
+    pub max_rounds: u32,
+    pub game_started: bool,
+    pub host_id: Option<String>,
+    pub ready_players: HashSet<String>,
+    /// Kick votes currently in progress, keyed by the target's player id to
+    /// the set of players who have voted yes to kick them.
+    pub kick_votes: HashMap<String, HashSet<String>>,
+}
+
+impl GameState {
+    pub fn new(mode: Box<dyn GameMode>) -> Self {
+        Self {
+            current_round: 1,
+            mode,
+            players: HashMap::new(),
+            active_connections: HashMap::new(),
+            round_active: false,
+            max_rounds: 5,
+            game_started: false,
+            host_id: None,
+            ready_players: HashSet::new(),
+            kick_votes: HashMap::new(),
+        }
+    }
+
+    /// Moves the room out of the lobby and into round 1. Called once the
+    /// host starts the game, or once every connected player is ready.
+    pub fn start_game(&mut self) {
+        self.game_started = true;
+        self.current_round = 1;
+        self.round_active = true;
+        self.kick_votes.clear();
+
+        let secret = self.mode.new_round();
+        println!("Game started: {}", secret.log_description);
+    }
+
+    pub fn reset_for_next_round(&mut self) {
+        self.current_round += 1;
+        self.round_active = true;
+        self.kick_votes.clear();
+        // Keep connections alive - don't clear active_connections or mark players inactive
+
+        let secret = self.mode.new_round();
+        println!("Round {} started: {}", self.current_round, secret.log_description);
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.current_round > self.max_rounds
+    }
+
+    pub fn max_players(&self) -> usize {
+        self.mode.max_players()
+    }
+
+    pub fn min_players(&self) -> usize {
+        self.mode.min_players()
+    }
+
+    /// Whether the room currently has enough active connections for its
+    /// mode to ever resolve a round, i.e. it's safe to start.
+    pub fn has_enough_players_to_start(&self) -> bool {
+        self.active_connections.len() >= self.min_players()
+    }
+
+    pub fn can_accept_new_connection(&self) -> bool {
+        // Only allow new players to join while the room is still in its lobby phase
+        self.active_connections.len() < self.max_players() && !self.game_started && !self.is_game_over()
+    }
+
+    pub fn get_leaderboard(&self) -> Vec<(String, u32)> {
+        let mut leaderboard: Vec<(String, u32)> = self
+            .players
+            .values()
+            .map(|p| (p.name.clone(), p.wins))
+            .collect();
+        leaderboard.sort_by(|a, b| b.1.cmp(&a.1));
+        leaderboard
+    }
+
+    /// True if `name` is already in use by another active connection
+    /// (case-insensitive), so a new player should be asked to pick a
+    /// different nickname before joining.
+    fn is_name_taken(&self, name: &str, excluding_player_id: &str) -> bool {
+        self.active_connections
+            .iter()
+            .any(|(id, existing)| id != excluding_player_id && existing.eq_ignore_ascii_case(name))
+    }
+
+    /// Checks the name-uniqueness rule and inserts the player under the same
+    /// lock acquisition, so two connections racing to claim the same name
+    /// can't both pass the check before either is recorded.
+    pub fn add_or_reconnect_player(
+        &mut self,
+        player_id: String,
+        player_name: String,
+        sender: PlayerTransport,
+    ) -> JoinResult {
+        // Reconnecting players keep their existing name-collision exemption.
+        if self.players.contains_key(&player_id) {
+            if self.is_name_taken(&player_name, &player_id) {
+                return JoinResult::NameTaken;
+            }
+            let existing_player = self.players.get_mut(&player_id).unwrap();
+            existing_player.sender = sender;
+            existing_player.active = true;
+            existing_player.name = player_name.clone();
+            existing_player.last_pong = Instant::now();
+            self.active_connections.insert(player_id, player_name);
+            return JoinResult::Joined;
+        }
+
+        if self.is_name_taken(&player_name, &player_id) {
+            return JoinResult::NameTaken;
+        }
+
+        if self.active_connections.len() >= self.max_players() {
+            return JoinResult::RoomFull;
+        }
+
+        let player = Player {
+            id: player_id.clone(),
+            name: player_name.clone(),
+            sender,
+            wins: 0,
+            active: true,
+            last_pong: Instant::now(),
+        };
+        self.players.insert(player_id.clone(), player);
+        self.active_connections.insert(player_id.clone(), player_name);
+
+        // The first joiner becomes the host and can start the game early.
+        if self.host_id.is_none() {
+            self.host_id = Some(player_id);
+        }
+        JoinResult::Joined
+    }
+
+    pub fn remove_active_connection(&mut self, player_id: &str) {
+        self.active_connections.remove(player_id);
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.active = false;
+        }
+        self.kick_votes.remove(player_id);
+        for voters in self.kick_votes.values_mut() {
+            voters.remove(player_id);
+        }
+    }
+
+    pub fn get_active_player_count(&self) -> usize {
+        self.active_connections.len()
+    }
+
+    /// Records that a pong frame came back from this player, resetting
+    /// their heartbeat grace window.
+    pub fn touch_pong(&mut self, player_id: &str) {
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.last_pong = Instant::now();
+        }
+    }
+
+    pub fn is_host(&self, player_id: &str) -> bool {
+        self.host_id.as_deref() == Some(player_id)
+    }
+
+    pub fn set_ready(&mut self, player_id: &str) {
+        self.ready_players.insert(player_id.to_string());
+    }
+
+    /// True once every currently-connected player has marked themselves
+    /// ready and there are enough of them for the mode to actually play.
+    pub fn all_ready(&self) -> bool {
+        self.has_enough_players_to_start()
+            && self.active_connections.keys().all(|id| self.ready_players.contains(id))
+    }
+
+    /// Resolves a vote-kick target given either their player id (as sent in
+    /// the roster) or their name (as a netcat client would type it).
+    pub fn resolve_target(&self, query: &str) -> Option<String> {
+        if self.active_connections.contains_key(query) {
+            return Some(query.to_string());
+        }
+        self.active_connections
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(query))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Starts (or adds the caller's yes ballot to) the kick vote against
+    /// `target_id`. Returns the current yes-vote count.
+    pub fn propose_kick(&mut self, target_id: &str, voter_id: &str) -> usize {
+        let voters = self.kick_votes.entry(target_id.to_string()).or_default();
+        voters.insert(voter_id.to_string());
+        voters.len()
+    }
+
+    /// Records `voter_id`'s ballot on the in-progress vote against
+    /// `target_id`. Returns `None` if no vote against that target is active.
+    pub fn cast_kick_vote(&mut self, target_id: &str, voter_id: &str, yes: bool) -> Option<usize> {
+        let voters = self.kick_votes.get_mut(target_id)?;
+        if yes {
+            voters.insert(voter_id.to_string());
+        } else {
+            voters.remove(voter_id);
+        }
+        let count = voters.len();
+        if count == 0 {
+            self.kick_votes.remove(target_id);
+        }
+        Some(count)
+    }
+
+    /// The single kick vote currently in progress, if any.
+    pub fn active_kick_vote_target(&self) -> Option<String> {
+        self.kick_votes.keys().next().cloned()
+    }
+
+    /// Strict majority of currently active connections: more than half
+    /// (2 of 3, 3 of 4, and both players in a 2-player room).
+    pub fn kick_vote_threshold(&self) -> usize {
+        self.active_connections.len() / 2 + 1
+    }
+
+    pub fn roster(&self) -> Vec<RosterEntry> {
+        let mut entries: Vec<RosterEntry> = self
+            .active_connections
+            .iter()
+            .map(|(id, name)| RosterEntry {
+                player_id: id.clone(),
+                name: name.clone(),
+                ready: self.ready_players.contains(id),
+                host: self.is_host(id),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::{NumberGuess, RockPaperScissors};
+
+    fn test_sender() -> PlayerTransport {
+        PlayerTransport::Tcp(tokio::sync::mpsc::channel::<String>(4).0)
+    }
+
+    #[test]
+    fn first_joiner_becomes_host() {
+        let mut state = GameState::new(Box::new(NumberGuess::default()));
+        let result = state.add_or_reconnect_player("p1".to_string(), "Alice".to_string(), test_sender());
+        assert_eq!(result, JoinResult::Joined);
+        assert!(state.is_host("p1"));
+    }
+
+    #[test]
+    fn duplicate_name_is_rejected_case_insensitively() {
+        let mut state = GameState::new(Box::new(NumberGuess::default()));
+        state.add_or_reconnect_player("p1".to_string(), "Alice".to_string(), test_sender());
+
+        let result = state.add_or_reconnect_player("p2".to_string(), "ALICE".to_string(), test_sender());
+        assert_eq!(result, JoinResult::NameTaken);
+    }
+
+    #[test]
+    fn room_rejects_players_past_its_mode_capacity() {
+        let mut state = GameState::new(Box::new(RockPaperScissors::default()));
+        state.add_or_reconnect_player("p1".to_string(), "Alice".to_string(), test_sender());
+        state.add_or_reconnect_player("p2".to_string(), "Bob".to_string(), test_sender());
+
+        let result = state.add_or_reconnect_player("p3".to_string(), "Carol".to_string(), test_sender());
+        assert_eq!(result, JoinResult::RoomFull);
+    }
+
+    #[test]
+    fn all_ready_requires_the_modes_minimum_player_count() {
+        let mut state = GameState::new(Box::new(RockPaperScissors::default()));
+        state.add_or_reconnect_player("p1".to_string(), "Alice".to_string(), test_sender());
+        state.set_ready("p1");
+        assert!(!state.all_ready(), "a single ready player shouldn't be enough for a 2-player mode");
+
+        state.add_or_reconnect_player("p2".to_string(), "Bob".to_string(), test_sender());
+        assert!(!state.all_ready(), "Bob hasn't readied up yet");
+
+        state.set_ready("p2");
+        assert!(state.all_ready());
+    }
+
+    #[test]
+    fn kick_vote_reaches_its_majority_threshold() {
+        let mut state = GameState::new(Box::new(NumberGuess::default()));
+        state.add_or_reconnect_player("p1".to_string(), "Alice".to_string(), test_sender());
+        state.add_or_reconnect_player("p2".to_string(), "Bob".to_string(), test_sender());
+        state.add_or_reconnect_player("p3".to_string(), "Carol".to_string(), test_sender());
+
+        assert_eq!(state.kick_vote_threshold(), 2);
+
+        let count = state.propose_kick("p3", "p1");
+        assert_eq!(count, 1);
+        assert!(count < state.kick_vote_threshold());
+
+        let count = state.cast_kick_vote("p3", "p2", true).unwrap();
+        assert_eq!(count, 2);
+        assert!(count >= state.kick_vote_threshold());
+    }
+
+    #[test]
+    fn withdrawing_the_only_kick_vote_clears_it() {
+        let mut state = GameState::new(Box::new(NumberGuess::default()));
+        state.propose_kick("p2", "p1");
+        assert_eq!(state.active_kick_vote_target().as_deref(), Some("p2"));
+
+        state.cast_kick_vote("p2", "p1", false);
+        assert_eq!(state.active_kick_vote_target(), None);
+    }
+
+    #[test]
+    fn roster_is_sorted_by_name_and_reports_host_and_ready_flags() {
+        let mut state = GameState::new(Box::new(NumberGuess::default()));
+        state.add_or_reconnect_player("p1".to_string(), "Zed".to_string(), test_sender());
+        state.add_or_reconnect_player("p2".to_string(), "Amy".to_string(), test_sender());
+        state.set_ready("p2");
+
+        let roster = state.roster();
+        assert_eq!(roster[0].name, "Amy");
+        assert!(roster[0].ready);
+        assert!(!roster[0].host);
+        assert_eq!(roster[1].name, "Zed");
+        assert!(roster[1].host);
+        assert!(!roster[1].ready);
+    }
+}
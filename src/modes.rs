@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+/// What a mode needs to kick off a new round. Not sent to clients directly —
+/// just enough for the server to log what changed.
+pub struct RoundSecret {
+    pub log_description: String,
+}
+
+/// Everything a mode needs to evaluate one player's move beyond the raw
+/// input text: who's acting, the room's full active roster (for modes where
+/// the winner isn't necessarily the player who just acted), and the round
+/// number (for building result messages).
+pub struct RoundContext<'a> {
+    pub player_id: &'a str,
+    pub player_name: &'a str,
+    pub current_round: u32,
+    pub active_connections: &'a HashMap<String, String>,
+}
+
+/// The result of one player's move against the current round state.
+pub enum Outcome {
+    /// Didn't win, but was below the target.
+    TooLow,
+    /// Didn't win, but was above the target.
+    TooHigh,
+    /// The round is won. `winner_id` is who gets credited with it — not
+    /// necessarily the player whose input triggered resolution (e.g. in
+    /// rock-paper-scissors the last submitter can be the loser). `message`
+    /// is broadcast to the room as-is.
+    Win { winner_id: String, message: String },
+    /// Input couldn't be parsed/understood for this mode.
+    Invalid,
+    /// The move was recorded but the round isn't settled yet — other
+    /// players still need to act. The optional message is broadcast if
+    /// present (e.g. to announce a tie that needs a replay).
+    Pending(Option<String>),
+}
+
+/// A swappable round-resolution strategy. `GameState` owns the generic
+/// lobby/round/leaderboard machinery and defers only "what's this round's
+/// secret" and "did this input win" to whichever mode a room was created
+/// with.
+pub trait GameMode: std::fmt::Debug + Send + Sync {
+    fn new_round(&mut self) -> RoundSecret;
+    fn evaluate(&mut self, input: &str, ctx: &RoundContext) -> Outcome;
+    fn describe_rules(&self) -> String;
+
+    /// Stable identifier for this mode, matching the name `mode_from_name`
+    /// accepts. Used to keep auto-matching from mixing two modes in one room.
+    fn name(&self) -> &'static str;
+
+    /// How many players a room running this mode can seat at once.
+    fn max_players(&self) -> usize {
+        4
+    }
+
+    /// Fewest active connections a round needs to ever resolve. The game
+    /// can't be started below this, so a mode can't begin stuck waiting on a
+    /// move from a player who will never connect. Only the game start is
+    /// gated on it; later rounds start whatever the number of connections.
+    fn min_players(&self) -> usize {
+        1
+    }
+}
+
+/// The original game: guess a number between 1 and 100.
+#[derive(Debug, Default)]
+pub struct NumberGuess {
+    secret: u32,
+}
+
+impl GameMode for NumberGuess {
+    fn new_round(&mut self) -> RoundSecret {
+        self.secret = rand::thread_rng().gen_range(1..=100);
+        RoundSecret {
+            log_description: format!("secret number {}", self.secret),
+        }
+    }
+
+    fn evaluate(&mut self, input: &str, ctx: &RoundContext) -> Outcome {
+        let Ok(guess) = input.trim().parse::<u32>() else {
+            return Outcome::Invalid;
+        };
+        if !(1..=100).contains(&guess) {
+            return Outcome::Invalid;
+        }
+
+        match guess.cmp(&self.secret) {
+            std::cmp::Ordering::Less => Outcome::TooLow,
+            std::cmp::Ordering::Greater => Outcome::TooHigh,
+            std::cmp::Ordering::Equal => Outcome::Win {
+                winner_id: ctx.player_id.to_string(),
+                message: format!(
+                    "🎉 {} guessed {} → WINS ROUND {}! 🎉",
+                    ctx.player_name, guess, ctx.current_round
+                ),
+            },
+        }
+    }
+
+    fn describe_rules(&self) -> String {
+        "Guess the number between 1-100".to_string()
+    }
+
+    fn name(&self) -> &'static str {
+        "number-guess"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Choice {
+    Rock,
+    Paper,
+    Scissors,
+}
+
+impl Choice {
+    fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "rock" => Some(Choice::Rock),
+            "paper" => Some(Choice::Paper),
+            "scissors" => Some(Choice::Scissors),
+            _ => None,
+        }
+    }
+
+    fn beats(self, other: Choice) -> bool {
+        matches!(
+            (self, other),
+            (Choice::Rock, Choice::Scissors) | (Choice::Paper, Choice::Rock) | (Choice::Scissors, Choice::Paper)
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Choice::Rock => "rock",
+            Choice::Paper => "paper",
+            Choice::Scissors => "scissors",
+        }
+    }
+}
+
+/// Rock-paper-scissors for two players: each submits a choice and the round
+/// resolves as soon as both have played. Rooms running this mode are capped
+/// at 2 players since the showdown is pairwise.
+#[derive(Debug, Default)]
+pub struct RockPaperScissors {
+    choices: HashMap<String, Choice>,
+}
+
+impl GameMode for RockPaperScissors {
+    fn new_round(&mut self) -> RoundSecret {
+        self.choices.clear();
+        RoundSecret {
+            log_description: "new rock-paper-scissors round".to_string(),
+        }
+    }
+
+    fn evaluate(&mut self, input: &str, ctx: &RoundContext) -> Outcome {
+        let Some(choice) = Choice::parse(input) else {
+            return Outcome::Invalid;
+        };
+        self.choices.insert(ctx.player_id.to_string(), choice);
+
+        if ctx.active_connections.len() < 2
+            || !ctx.active_connections.keys().all(|id| self.choices.contains_key(id))
+        {
+            return Outcome::Pending(None);
+        }
+
+        let mut entries: Vec<(String, Choice)> = ctx
+            .active_connections
+            .keys()
+            .map(|id| (id.clone(), self.choices[id]))
+            .collect();
+        let (id_a, choice_a) = entries.remove(0);
+        let (id_b, choice_b) = entries.remove(0);
+
+        if choice_a == choice_b {
+            self.choices.clear();
+            return Outcome::Pending(Some("Tie! Both players picked the same move — play again.".to_string()));
+        }
+
+        let (winner_id, winner_choice, loser_choice) = if choice_a.beats(choice_b) {
+            (id_a, choice_a, choice_b)
+        } else {
+            (id_b, choice_b, choice_a)
+        };
+        let winner_name = ctx.active_connections.get(&winner_id).cloned().unwrap_or_else(|| winner_id.clone());
+
+        Outcome::Win {
+            winner_id,
+            message: format!(
+                "🎉 {} played {} → beats {}, WINS ROUND {}! 🎉",
+                winner_name, winner_choice.name(), loser_choice.name(), ctx.current_round
+            ),
+        }
+    }
+
+    fn describe_rules(&self) -> String {
+        "Type 'rock', 'paper', or 'scissors'. The round resolves once both players have played".to_string()
+    }
+
+    fn max_players(&self) -> usize {
+        2
+    }
+
+    fn min_players(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "rps"
+    }
+}
+
+/// Picks a mode by name for a freshly created room, defaulting to the
+/// original number-guessing game when `name` is absent or unrecognized.
+pub fn mode_from_name(name: Option<&str>) -> Box<dyn GameMode> {
+    match name.map(|n| n.to_lowercase()) {
+        Some(n) if n == "rps" || n == "rock-paper-scissors" => Box::new(RockPaperScissors::default()),
+        _ => Box::new(NumberGuess::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx<'a>(player_id: &'a str, player_name: &'a str, active_connections: &'a HashMap<String, String>) -> RoundContext<'a> {
+        RoundContext {
+            player_id,
+            player_name,
+            current_round: 1,
+            active_connections,
+        }
+    }
+
+    #[test]
+    fn number_guess_reports_too_low_and_too_high_and_wins() {
+        let mut mode = NumberGuess { secret: 50 };
+        let active = HashMap::new();
+
+        assert!(matches!(mode.evaluate("10", &ctx("p1", "Alice", &active)), Outcome::TooLow));
+        assert!(matches!(mode.evaluate("90", &ctx("p1", "Alice", &active)), Outcome::TooHigh));
+        assert!(matches!(mode.evaluate("not a number", &ctx("p1", "Alice", &active)), Outcome::Invalid));
+
+        match mode.evaluate("50", &ctx("p1", "Alice", &active)) {
+            Outcome::Win { winner_id, .. } => assert_eq!(winner_id, "p1"),
+            other => panic!("expected a win, got {:?}", std::mem::discriminant(&other)),
+        }
+    }
+
+    #[test]
+    fn rps_stays_pending_until_both_players_have_moved() {
+        let mut mode = RockPaperScissors::default();
+        let mut active = HashMap::new();
+        active.insert("p1".to_string(), "Alice".to_string());
+        active.insert("p2".to_string(), "Bob".to_string());
+
+        let outcome = mode.evaluate("rock", &ctx("p1", "Alice", &active));
+        assert!(matches!(outcome, Outcome::Pending(None)));
+    }
+
+    #[test]
+    fn rps_resolves_once_both_players_have_moved() {
+        let mut mode = RockPaperScissors::default();
+        let mut active = HashMap::new();
+        active.insert("p1".to_string(), "Alice".to_string());
+        active.insert("p2".to_string(), "Bob".to_string());
+
+        mode.evaluate("rock", &ctx("p1", "Alice", &active));
+        match mode.evaluate("scissors", &ctx("p2", "Bob", &active)) {
+            Outcome::Win { winner_id, .. } => assert_eq!(winner_id, "p1"),
+            other => panic!("expected rock to beat scissors, got {:?}", std::mem::discriminant(&other)),
+        }
+    }
+
+    #[test]
+    fn rps_ties_clear_choices_and_ask_for_a_replay() {
+        let mut mode = RockPaperScissors::default();
+        let mut active = HashMap::new();
+        active.insert("p1".to_string(), "Alice".to_string());
+        active.insert("p2".to_string(), "Bob".to_string());
+
+        mode.evaluate("paper", &ctx("p1", "Alice", &active));
+        let outcome = mode.evaluate("paper", &ctx("p2", "Bob", &active));
+        assert!(matches!(outcome, Outcome::Pending(Some(_))));
+        assert!(mode.choices.is_empty());
+    }
+
+    #[test]
+    fn mode_from_name_picks_the_requested_mode() {
+        assert_eq!(mode_from_name(None).name(), "number-guess");
+        assert_eq!(mode_from_name(Some("rps")).name(), "rps");
+        assert_eq!(mode_from_name(Some("ROCK-PAPER-SCISSORS")).name(), "rps");
+    }
+}
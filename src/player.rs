@@ -0,0 +1,13 @@
+use std::time::Instant;
+
+use crate::transport::PlayerTransport;
+
+#[derive(Clone, Debug)]
+pub struct Player {
+    pub id: String,
+    pub name: String,
+    pub sender: PlayerTransport,
+    pub wins: u32,
+    pub active: bool, // Track if player is currently connected
+    pub last_pong: Instant, // Last heartbeat pong seen from this player (WS only)
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever a breaking change is made to `ServerMessage`/`ClientMessage`.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// One row of the lobby roster sent to clients while a room waits to start.
+#[derive(Clone, Debug, Serialize)]
+pub struct RosterEntry {
+    pub player_id: String,
+    pub name: String,
+    pub ready: bool,
+    pub host: bool,
+}
+
+/// Every frame the server can send, tagged by `type` so a frontend can parse
+/// it reliably instead of pattern-matching free-form strings.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    /// Sent once, immediately after the socket is accepted into a room.
+    Welcome { version: u32, player_id: String },
+    Prompt { text: String },
+    /// The lobby roster, re-sent whenever someone joins, readies up, or is
+    /// voted out.
+    Roster { players: Vec<RosterEntry> },
+    /// Broadcast whenever a player's move resolves to a result, whatever
+    /// the room's game mode. `input` is the raw move as typed/sent.
+    GuessResult { input: String, outcome: String },
+    Leaderboard { entries: Vec<(String, u32)> },
+    /// `rules` is the room's mode's own one-line description, so clients
+    /// always see what counts as a valid move for the mode they're in.
+    RoundStart { round: u32, max_rounds: u32, rules: String },
+    GameOver { standings: Vec<(String, u32)> },
+}
+
+impl ServerMessage {
+    pub fn prompt(text: impl Into<String>) -> Self {
+        ServerMessage::Prompt { text: text.into() }
+    }
+
+    /// Plain-text rendering kept for netcat-style clients that can't parse
+    /// JSON frames, so the old experience still works over raw TCP.
+    pub fn to_plaintext(&self) -> String {
+        match self {
+            ServerMessage::Welcome { player_id, .. } => {
+                format!("Welcome! You are Player {}. Please enter your name:", player_id)
+            }
+            ServerMessage::Prompt { text } => text.clone(),
+            ServerMessage::Roster { players } => {
+                let mut out = "Lobby:\n".to_string();
+                for player in players {
+                    let host_tag = if player.host { " (host)" } else { "" };
+                    let ready_tag = if player.ready { " [ready]" } else { "" };
+                    out.push_str(&format!("- {}{}{}\n", player.name, host_tag, ready_tag));
+                }
+                out
+            }
+            ServerMessage::GuessResult { outcome, .. } => outcome.clone(),
+            ServerMessage::Leaderboard { entries } => {
+                let mut out = "Current Standings:\n".to_string();
+                for (i, (name, wins)) in entries.iter().enumerate() {
+                    out.push_str(&format!("{}. {} - {} wins\n", i + 1, name, wins));
+                }
+                out
+            }
+            ServerMessage::RoundStart { round, rules, .. } => {
+                format!("🎮 ROUND {} STARTED! 🎮\n{}", round, rules)
+            }
+            ServerMessage::GameOver { standings } => {
+                let mut out = "🏆 GAME OVER - FINAL RESULTS 🏆\n".to_string();
+                if !standings.is_empty() {
+                    out.push_str("Final Standings:\n");
+                    for (i, (name, wins)) in standings.iter().enumerate() {
+                        let medal = match i {
+                            0 => "🥇",
+                            1 => "🥈",
+                            2 => "🥉",
+                            _ => "  ",
+                        };
+                        out.push_str(&format!("{} {}. {} - {} wins\n", medal, i + 1, name, wins));
+                    }
+                    if let Some((winner, wins)) = standings.first() {
+                        out.push_str(&format!(
+                            "\n🎊 Congratulations {}! You are the champion with {} wins! 🎊",
+                            winner, wins
+                        ));
+                    }
+                }
+                out.push_str("\nThank you for playing! Connection will close shortly.");
+                out
+            }
+        }
+    }
+}
+
+/// Every frame the server accepts from a client, tagged by `type`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    SetName { name: String },
+    /// Marks the sender as ready to start; once every connected player is
+    /// ready the room starts round 1 without needing the host.
+    Ready,
+    /// Host-only: starts the game immediately, regardless of who's ready.
+    StartGame,
+    /// A move for whatever mode the room is running — a number for
+    /// `NumberGuess`, "rock"/"paper"/"scissors" for `RockPaperScissors`, and
+    /// so on. Validity is judged by the mode itself, not this protocol.
+    Play { input: String },
+    /// Starts, or adds the sender's yes ballot to, a kick vote against
+    /// `target` (a player id or, for netcat clients, a name).
+    VoteKick { target: String },
+    /// Casts a ballot on whichever kick vote is currently in progress.
+    Vote { yes: bool },
+}
+
+/// Parses one line of client input, accepting either a tagged JSON frame or
+/// the old free-form text a netcat client would type. `name_set` decides how
+/// a plain-text (non-JSON) line is interpreted: before a name is set it's
+/// always a `SetName`; after, it's a recognized keyword or else a `Play`
+/// whose `input` is handed to the room's mode to judge.
+pub fn parse_client_message(line: &str, name_set: bool) -> ClientMessage {
+    if let Ok(msg) = serde_json::from_str::<ClientMessage>(line) {
+        // Before a name is set, only a `SetName` frame is honored — any
+        // other JSON frame falls through to the same "treat the whole line
+        // as a name" rule plain text gets, instead of letting a client skip
+        // straight to playing, readying up, or voting.
+        if name_set || matches!(msg, ClientMessage::SetName { .. }) {
+            return msg;
+        }
+    }
+
+    let trimmed = line.trim();
+
+    if !name_set {
+        return ClientMessage::SetName {
+            name: trimmed.to_string(),
+        };
+    }
+
+    let lowercased = trimmed.to_lowercase();
+
+    match lowercased.as_str() {
+        "ready" => return ClientMessage::Ready,
+        "start" => return ClientMessage::StartGame,
+        "yes" => return ClientMessage::Vote { yes: true },
+        "no" => return ClientMessage::Vote { yes: false },
+        _ => {}
+    }
+
+    if lowercased.starts_with("kick ") {
+        // `starts_with` is UTF-8 aware, so this never slices mid-character
+        // the way a raw `trimmed[..5]` byte index could on non-ASCII input.
+        let target: String = trimmed.chars().skip("kick ".chars().count()).collect();
+        return ClientMessage::VoteKick {
+            target: target.trim().to_string(),
+        };
+    }
+
+    ClientMessage::Play {
+        input: trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_before_name_is_always_set_name() {
+        let msg = parse_client_message("Alice", false);
+        assert!(matches!(msg, ClientMessage::SetName { name } if name == "Alice"));
+    }
+
+    #[test]
+    fn recognized_keywords_parse_after_name_is_set() {
+        assert!(matches!(parse_client_message("ready", true), ClientMessage::Ready));
+        assert!(matches!(parse_client_message("START", true), ClientMessage::StartGame));
+        assert!(matches!(parse_client_message("yes", true), ClientMessage::Vote { yes: true }));
+        assert!(matches!(parse_client_message("No", true), ClientMessage::Vote { yes: false }));
+    }
+
+    #[test]
+    fn kick_prefix_is_parsed_case_insensitively() {
+        let msg = parse_client_message("KICK Bob", true);
+        assert!(matches!(msg, ClientMessage::VoteKick { target } if target == "Bob"));
+    }
+
+    #[test]
+    fn unrecognized_text_after_name_is_a_play() {
+        let msg = parse_client_message("42", true);
+        assert!(matches!(msg, ClientMessage::Play { input } if input == "42"));
+    }
+
+    #[test]
+    fn tagged_json_frames_are_parsed_directly() {
+        let msg = parse_client_message(r#"{"type":"Play","input":"rock"}"#, true);
+        assert!(matches!(msg, ClientMessage::Play { input } if input == "rock"));
+    }
+
+    /// Regression test: a JSON frame other than `SetName` sent before a name
+    /// is set used to bypass the "first message is the name" contract.
+    #[test]
+    fn non_set_name_json_frames_are_rejected_before_a_name_is_set() {
+        let msg = parse_client_message(r#"{"type":"Play","input":"50"}"#, false);
+        assert!(matches!(msg, ClientMessage::SetName { .. }));
+    }
+
+    #[test]
+    fn set_name_json_frame_is_honored_before_a_name_is_set() {
+        let msg = parse_client_message(r#"{"type":"SetName","name":"Alice"}"#, false);
+        assert!(matches!(msg, ClientMessage::SetName { name } if name == "Alice"));
+    }
+
+    /// Regression test: a line whose 5th byte lands inside a multibyte
+    /// character used to panic `parse_client_message` because the old
+    /// "kick " check byte-sliced the string before confirming the prefix.
+    #[test]
+    fn multibyte_input_does_not_panic() {
+        let msg = parse_client_message("aaaaé", true);
+        assert!(matches!(msg, ClientMessage::Play { input } if input == "aaaaé"));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+use crate::game::GameState;
+use crate::modes::{self, GameMode};
+
+/// A single in-progress game, with its own players and round state.
+/// Mirrors the lobby/room split used by room-based game servers so the
+/// server can host many simultaneous games instead of one global match.
+pub struct Room {
+    pub id: String,
+    pub game_state: GameState,
+    /// Set once the heartbeat sweep task has been spawned for this room, so
+    /// it's only started by whichever connection happens to join first.
+    pub heartbeat_started: bool,
+    /// Back-reference to the lobby this room was created in, so any code
+    /// holding just a `Room` can recycle it once it's empty instead of
+    /// needing the lobby threaded through every call.
+    pub lobby: Arc<Mutex<Lobby>>,
+}
+
+impl Room {
+    pub fn new(mode: Box<dyn GameMode>, lobby: Arc<Mutex<Lobby>>) -> Self {
+        Self::with_id(Uuid::new_v4().to_string()[..8].to_uppercase(), mode, lobby)
+    }
+
+    pub fn with_id(id: String, mode: Box<dyn GameMode>, lobby: Arc<Mutex<Lobby>>) -> Self {
+        Self {
+            id,
+            game_state: GameState::new(mode),
+            heartbeat_started: false,
+            lobby,
+        }
+    }
+
+    pub fn can_accept_new_connection(&self) -> bool {
+        self.game_state.can_accept_new_connection()
+    }
+}
+
+/// Holds every live room, keyed by room id.
+#[derive(Default)]
+pub struct Lobby {
+    pub rooms: HashMap<String, Arc<Mutex<Room>>>,
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self {
+            rooms: HashMap::new(),
+        }
+    }
+
+    /// Finds an open room whose mode matches `mode_name`, so an auto-matched
+    /// connection never lands in a room running a different game than it
+    /// asked for.
+    fn find_joinable_room(&self, mode_name: Option<&str>) -> Option<Arc<Mutex<Room>>> {
+        let wanted = modes::mode_from_name(mode_name).name();
+        self.rooms
+            .values()
+            .find(|room| {
+                let room = room.lock().unwrap();
+                room.can_accept_new_connection() && room.game_state.mode.name() == wanted
+            })
+            .cloned()
+    }
+
+    /// Routes a connection into the room requested by id, or auto-matches it
+    /// into any room of the requested mode that still has space, spawning a
+    /// fresh one if every existing matching room is full. `mode_name` picks
+    /// the game mode for a newly-created room and is also used to filter
+    /// auto-matching so a room never mixes two modes. `self_handle` is the
+    /// same `Arc<Mutex<Lobby>>` wrapping `self`, handed to a freshly created
+    /// room so it can recycle itself once empty.
+    pub fn join_room(&mut self, self_handle: Arc<Mutex<Lobby>>, requested_room_id: Option<&str>, mode_name: Option<&str>) -> Arc<Mutex<Room>> {
+        if let Some(id) = requested_room_id {
+            if let Some(room) = self.rooms.get(id) {
+                return room.clone();
+            }
+            let room = Arc::new(Mutex::new(Room::with_id(id.to_string(), modes::mode_from_name(mode_name), self_handle)));
+            self.rooms.insert(id.to_string(), room.clone());
+            return room;
+        }
+
+        if let Some(room) = self.find_joinable_room(mode_name) {
+            return room;
+        }
+
+        let room = Arc::new(Mutex::new(Room::new(modes::mode_from_name(mode_name), self_handle)));
+        self.rooms.insert(room.lock().unwrap().id.clone(), room.clone());
+        room
+    }
+
+    /// Drops a room from the lobby — called once a game finishes, or once a
+    /// room's last active connection is gone — so it's freed instead of
+    /// lingering in the map forever (once `game_started` a room can never be
+    /// auto-matched back into, so it would otherwise never be reclaimed).
+    pub fn remove_room(&mut self, room_id: &str) {
+        self.rooms.remove(room_id);
+    }
+}
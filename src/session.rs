@@ -0,0 +1,580 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::game::JoinResult;
+use crate::modes::{Outcome, RoundContext};
+use crate::protocol::{parse_client_message, ClientMessage, ServerMessage};
+use crate::room::Room;
+use crate::transport::{PlayerTransport, SendOutcome};
+use crate::AppState;
+
+/// How often a room's heartbeat sweep pings its WS players.
+const HEARTBEAT_INTERVAL_SECS: u64 = 15;
+/// How long a player can go without a pong before they're treated as dead.
+const PONG_GRACE_SECS: u64 = 30;
+
+/// Per-connection view of an in-progress name/move exchange. Both the
+/// WebSocket and the plain-TCP gateway drive the same state machine here so
+/// the round logic in `GameState` only has to be written once.
+pub struct PlayerSession {
+    pub player_id: String,
+    pub player_name: String,
+    pub name_set: bool,
+    pub tx: PlayerTransport,
+    pub room: Arc<Mutex<Room>>,
+}
+
+/// What the transport-specific read loop should do after handling one line.
+pub enum LineOutcome {
+    Continue,
+    GameOver,
+}
+
+impl PlayerSession {
+    pub fn new(player_id: String, tx: PlayerTransport, room: Arc<Mutex<Room>>) -> Self {
+        Self {
+            player_id,
+            player_name: String::new(),
+            name_set: false,
+            tx,
+            room,
+        }
+    }
+
+    /// Handles one line of inbound text, whatever the transport. Accepts
+    /// either a tagged `ClientMessage` JSON frame or the old free-form text
+    /// a netcat client would type.
+    pub async fn handle_line(&mut self, state: &AppState, text: &str) -> LineOutcome {
+        let message = parse_client_message(text, self.name_set);
+
+        match message {
+            ClientMessage::SetName { name } => self.handle_set_name(name).await,
+            ClientMessage::Ready => self.handle_ready().await,
+            ClientMessage::StartGame => self.handle_start_game().await,
+            ClientMessage::Play { input } => self.handle_play(state, input).await,
+            ClientMessage::VoteKick { target } => self.handle_vote_kick(target).await,
+            ClientMessage::Vote { yes } => self.handle_vote(yes).await,
+        }
+    }
+
+    async fn handle_set_name(&mut self, name: String) -> LineOutcome {
+        let join_result = {
+            let mut room = self.room.lock().unwrap();
+            room.game_state.add_or_reconnect_player(self.player_id.clone(), name.clone(), self.tx.clone())
+        };
+
+        match join_result {
+            JoinResult::NameTaken => {
+                self.tx.send_message(&ServerMessage::prompt(format!(
+                    "The name '{}' is already taken in this room. Please choose another:",
+                    name
+                )));
+                return LineOutcome::Continue;
+            }
+            JoinResult::RoomFull => {
+                self.tx.send_message(&ServerMessage::prompt(
+                    "Sorry, the room is now full. Please wait for the next round.",
+                ));
+                return LineOutcome::GameOver;
+            }
+            JoinResult::Joined => {}
+        }
+
+        self.player_name = name;
+        self.name_set = true;
+
+        let lobby_info = {
+            let room = self.room.lock().unwrap();
+            let is_host = room.game_state.is_host(&self.player_id);
+            format!(
+                "Welcome {}! Room {} - Players: {}/{}. Type 'ready' once you're set{}.",
+                self.player_name,
+                room.id,
+                room.game_state.get_active_player_count(),
+                room.game_state.max_players(),
+                if is_host { ", or 'start' to begin right away" } else { "" }
+            )
+        };
+        self.tx.send_message(&ServerMessage::prompt(lobby_info));
+
+        broadcast_roster(&self.room).await;
+        broadcast_to_others(&self.room, &self.player_id, &format!("{} joined the game!", self.player_name)).await;
+        LineOutcome::Continue
+    }
+
+    /// True once this connection has actually joined the room as a named
+    /// active player, rather than just holding an open socket — so lobby
+    /// and in-round commands can reject a connection that skipped `SetName`.
+    fn is_joined(&self) -> bool {
+        self.name_set && self.room.lock().unwrap().game_state.active_connections.contains_key(&self.player_id)
+    }
+
+    async fn handle_ready(&mut self) -> LineOutcome {
+        if !self.is_joined() {
+            self.tx.send_message(&ServerMessage::prompt("Please set your name before readying up."));
+            return LineOutcome::Continue;
+        }
+
+        let (already_started, all_ready) = {
+            let mut room = self.room.lock().unwrap();
+            if room.game_state.game_started {
+                (true, false)
+            } else {
+                room.game_state.set_ready(&self.player_id);
+                (false, room.game_state.all_ready())
+            }
+        };
+
+        if already_started {
+            return LineOutcome::Continue;
+        }
+
+        broadcast_roster(&self.room).await;
+
+        if all_ready {
+            self.start_game_now().await;
+        }
+
+        LineOutcome::Continue
+    }
+
+    async fn handle_start_game(&mut self) -> LineOutcome {
+        let (is_host, already_started, enough_players, min_players) = {
+            let room = self.room.lock().unwrap();
+            (
+                room.game_state.is_host(&self.player_id),
+                room.game_state.game_started,
+                room.game_state.has_enough_players_to_start(),
+                room.game_state.min_players(),
+            )
+        };
+
+        if already_started {
+            return LineOutcome::Continue;
+        }
+
+        if !is_host {
+            self.tx
+                .send_message(&ServerMessage::prompt("Only the host can start the game early."));
+            return LineOutcome::Continue;
+        }
+
+        if !enough_players {
+            self.tx.send_message(&ServerMessage::prompt(format!(
+                "Need at least {} player(s) for this mode before starting.",
+                min_players
+            )));
+            return LineOutcome::Continue;
+        }
+
+        self.start_game_now().await;
+        LineOutcome::Continue
+    }
+
+    async fn start_game_now(&self) {
+        let (round, max_rounds, rules) = {
+            let mut room = self.room.lock().unwrap();
+            room.game_state.start_game();
+            (room.game_state.current_round, room.game_state.max_rounds, room.game_state.mode.describe_rules())
+        };
+
+        broadcast_message_to_all(&self.room, &ServerMessage::RoundStart { round, max_rounds, rules }).await;
+    }
+
+    async fn handle_play(&mut self, state: &AppState, input: String) -> LineOutcome {
+        if !self.is_joined() {
+            self.tx.send_message(&ServerMessage::prompt("Please set your name before playing."));
+            return LineOutcome::Continue;
+        }
+
+        let (game_started, round_active) = {
+            let room = self.room.lock().unwrap();
+            (room.game_state.game_started, room.game_state.round_active)
+        };
+
+        if !game_started {
+            self.tx.send_message(&ServerMessage::prompt(
+                "Game hasn't started yet. Type 'ready', or ask the host to 'start'.",
+            ));
+            return LineOutcome::Continue;
+        }
+
+        if !round_active {
+            self.tx
+                .send_message(&ServerMessage::prompt("Round is over! Waiting for next round..."));
+            return LineOutcome::Continue;
+        }
+
+        let (outcome, current_round) = {
+            let mut room = self.room.lock().unwrap();
+            let current_round = room.game_state.current_round;
+            let active_connections = room.game_state.active_connections.clone();
+            let ctx = RoundContext {
+                player_id: &self.player_id,
+                player_name: &self.player_name,
+                current_round,
+                active_connections: &active_connections,
+            };
+            let outcome = room.game_state.mode.evaluate(&input, &ctx);
+            (outcome, current_round)
+        };
+
+        match outcome {
+            Outcome::Invalid => {
+                let hint = self.room.lock().unwrap().game_state.mode.describe_rules();
+                self.tx.send_message(&ServerMessage::prompt(format!("Invalid input. {}", hint)));
+                LineOutcome::Continue
+            }
+            Outcome::Pending(note) => {
+                if let Some(note) = note {
+                    broadcast_to_all(&self.room, &note).await;
+                } else {
+                    self.tx
+                        .send_message(&ServerMessage::prompt("Your move has been recorded. Waiting for other players..."));
+                }
+                LineOutcome::Continue
+            }
+            Outcome::TooLow => {
+                let outcome = format!("{} played {} → Too low! (Round {})", self.player_name, input, current_round);
+                broadcast_message_to_all(&self.room, &ServerMessage::GuessResult { input, outcome }).await;
+                LineOutcome::Continue
+            }
+            Outcome::TooHigh => {
+                let outcome = format!("{} played {} → Too high! (Round {})", self.player_name, input, current_round);
+                broadcast_message_to_all(&self.room, &ServerMessage::GuessResult { input, outcome }).await;
+                LineOutcome::Continue
+            }
+            Outcome::Win { winner_id, message } => {
+                let is_game_over = {
+                    let mut room = self.room.lock().unwrap();
+                    room.game_state.round_active = false;
+                    if let Some(player) = room.game_state.players.get_mut(&winner_id) {
+                        player.wins += 1;
+                    }
+                    current_round >= room.game_state.max_rounds
+                };
+
+                broadcast_message_to_all(&self.room, &ServerMessage::GuessResult { input, outcome: message }).await;
+
+                tokio::time::sleep(Duration::from_secs(2)).await;
+
+                if is_game_over {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    end_game(state, &self.room).await;
+                    LineOutcome::GameOver
+                } else {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    end_round_and_start_next(&self.room).await;
+                    LineOutcome::Continue
+                }
+            }
+        }
+    }
+
+    async fn handle_vote_kick(&mut self, target: String) -> LineOutcome {
+        if !self.is_joined() {
+            self.tx.send_message(&ServerMessage::prompt("Please set your name before voting to kick."));
+            return LineOutcome::Continue;
+        }
+
+        let resolved = {
+            let room = self.room.lock().unwrap();
+            room.game_state.resolve_target(&target)
+        };
+
+        let Some(target_id) = resolved else {
+            self.tx
+                .send_message(&ServerMessage::prompt(format!("No active player found matching '{}'.", target)));
+            return LineOutcome::Continue;
+        };
+
+        if target_id == self.player_id {
+            self.tx.send_message(&ServerMessage::prompt("You can't vote to kick yourself."));
+            return LineOutcome::Continue;
+        }
+
+        let in_other_vote = {
+            let room = self.room.lock().unwrap();
+            matches!(room.game_state.active_kick_vote_target(), Some(existing) if existing != target_id)
+        };
+        if in_other_vote {
+            self.tx
+                .send_message(&ServerMessage::prompt("Another kick vote is already in progress. Please wait for it to resolve."));
+            return LineOutcome::Continue;
+        }
+
+        self.register_kick_ballot(&target_id, true).await;
+        LineOutcome::Continue
+    }
+
+    async fn handle_vote(&mut self, yes: bool) -> LineOutcome {
+        if !self.is_joined() {
+            self.tx.send_message(&ServerMessage::prompt("Please set your name before voting."));
+            return LineOutcome::Continue;
+        }
+
+        let target_id = {
+            let room = self.room.lock().unwrap();
+            room.game_state.active_kick_vote_target()
+        };
+
+        let Some(target_id) = target_id else {
+            self.tx.send_message(&ServerMessage::prompt("There's no kick vote in progress."));
+            return LineOutcome::Continue;
+        };
+
+        self.register_kick_ballot(&target_id, yes).await;
+        LineOutcome::Continue
+    }
+
+    /// Casts a ballot on the vote against `target_id`, broadcasts the
+    /// updated tally, and carries out the kick if it just reached majority.
+    async fn register_kick_ballot(&mut self, target_id: &str, yes: bool) {
+        let (votes, threshold, target_name) = {
+            let mut room = self.room.lock().unwrap();
+            let votes = match room.game_state.cast_kick_vote(target_id, &self.player_id, yes) {
+                Some(votes) => votes,
+                None => room.game_state.propose_kick(target_id, &self.player_id),
+            };
+            let threshold = room.game_state.kick_vote_threshold();
+            let target_name = room.game_state.active_connections.get(target_id).cloned().unwrap_or_default();
+            (votes, threshold, target_name)
+        };
+
+        broadcast_to_all(
+            &self.room,
+            &format!("Vote to kick {}: {}/{} needed", target_name, votes, threshold),
+        )
+        .await;
+
+        if votes >= threshold {
+            self.execute_kick(target_id, &target_name).await;
+        }
+    }
+
+    async fn execute_kick(&self, target_id: &str, target_name: &str) {
+        let target_sender = {
+            let mut room = self.room.lock().unwrap();
+            let sender = room.game_state.players.get(target_id).map(|p| p.sender.clone());
+            room.game_state.remove_active_connection(target_id);
+            sender
+        };
+
+        if let Some(sender) = target_sender {
+            sender.send_message(&ServerMessage::prompt("You have been voted out of the room."));
+            sender.close();
+        }
+
+        broadcast_to_all(&self.room, &format!("{} was voted out of the room.", target_name)).await;
+        broadcast_roster(&self.room).await;
+    }
+
+    pub async fn handle_disconnect(&self) {
+        self.room.lock().unwrap().game_state.remove_active_connection(&self.player_id);
+
+        if self.name_set {
+            broadcast_to_others(&self.room, &self.player_id, &format!("{} left the game", self.player_name)).await;
+        }
+
+        recycle_if_empty(&self.room);
+    }
+}
+
+/// Drops `room` from its lobby once its last active connection is gone, so
+/// an abandoned lobby or a game everyone dropped out of mid-round doesn't
+/// linger in the map forever (once `game_started` it can never be
+/// auto-matched back into, so nothing else would ever reclaim it).
+fn recycle_if_empty(room: &Arc<Mutex<Room>>) {
+    let (room_id, lobby, is_empty) = {
+        let room = room.lock().unwrap();
+        (room.id.clone(), room.lobby.clone(), room.game_state.active_connections.is_empty())
+    };
+
+    if is_empty {
+        lobby.lock().unwrap().remove_room(&room_id);
+    }
+}
+
+async fn broadcast_roster(room: &Arc<Mutex<Room>>) {
+    let players = room.lock().unwrap().game_state.roster();
+    broadcast_message_to_all(room, &ServerMessage::Roster { players }).await;
+}
+
+pub async fn broadcast_message_to_all(room: &Arc<Mutex<Room>>, message: &ServerMessage) {
+    let (active_players, players) = {
+        let room = room.lock().unwrap();
+        (
+            room.game_state.active_connections.keys().cloned().collect::<Vec<_>>(),
+            room.game_state.players.clone(),
+        )
+    };
+
+    let mut fell_behind = Vec::new();
+    for player_id in active_players {
+        if let Some(player) = players.get(&player_id) {
+            if let SendOutcome::Full = player.sender.send_message(message) {
+                fell_behind.push(player_id);
+            }
+        }
+    }
+
+    evict_stalled_players(room, fell_behind).await;
+}
+
+pub async fn broadcast_to_all(room: &Arc<Mutex<Room>>, text: &str) {
+    broadcast_message_to_all(room, &ServerMessage::prompt(text)).await;
+}
+
+pub async fn broadcast_to_others(room: &Arc<Mutex<Room>>, sender_id: &str, message: &str) {
+    let (active_players, players) = {
+        let room = room.lock().unwrap();
+        (
+            room.game_state.active_connections.keys().cloned().collect::<Vec<_>>(),
+            room.game_state.players.clone(),
+        )
+    };
+
+    let payload = ServerMessage::prompt(message);
+    let mut fell_behind = Vec::new();
+    for player_id in active_players {
+        if player_id != sender_id {
+            if let Some(player) = players.get(&player_id) {
+                if let SendOutcome::Full = player.sender.send_message(&payload) {
+                    fell_behind.push(player_id);
+                }
+            }
+        }
+    }
+
+    evict_stalled_players(room, fell_behind).await;
+}
+
+/// Removes players whose outbound channel is full from the room's active
+/// connections rather than letting an unbounded backlog of stale messages
+/// pile up behind a slow client. Their socket itself is not closed here.
+async fn evict_stalled_players(room: &Arc<Mutex<Room>>, player_ids: Vec<String>) {
+    if player_ids.is_empty() {
+        return;
+    }
+
+    {
+        let mut room = room.lock().unwrap();
+        for player_id in &player_ids {
+            room.game_state.remove_active_connection(player_id);
+        }
+    }
+
+    for player_id in &player_ids {
+        println!("Player {} fell too far behind and was removed from the room", player_id);
+    }
+
+    recycle_if_empty(room);
+}
+
+/// Periodically pings every WS player in the room and evicts anyone who
+/// hasn't ponged back within the grace window. Started once, by whichever
+/// connection joins the room first.
+pub fn spawn_heartbeat(room: Arc<Mutex<Room>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+        interval.tick().await; // first tick fires immediately; skip it
+
+        loop {
+            interval.tick().await;
+
+            // Once only this task still holds the Arc, the room has been
+            // recycled out of the lobby and there's nothing left to ping.
+            if Arc::strong_count(&room) <= 1 {
+                break;
+            }
+
+            let (timed_out, to_ping) = {
+                let room = room.lock().unwrap();
+                let mut timed_out = Vec::new();
+                let mut to_ping = Vec::new();
+                for player in room.game_state.players.values() {
+                    if !player.active || !matches!(player.sender, PlayerTransport::Ws(_)) {
+                        continue;
+                    }
+                    if player.last_pong.elapsed() > Duration::from_secs(PONG_GRACE_SECS) {
+                        timed_out.push((player.id.clone(), player.name.clone()));
+                    } else {
+                        to_ping.push(player.sender.clone());
+                    }
+                }
+                (timed_out, to_ping)
+            };
+
+            for sender in to_ping {
+                sender.ping();
+            }
+
+            let any_timed_out = !timed_out.is_empty();
+            for (player_id, player_name) in timed_out {
+                room.lock().unwrap().game_state.remove_active_connection(&player_id);
+                broadcast_to_others(&room, &player_id, &format!("{} timed out", player_name)).await;
+            }
+
+            if any_timed_out {
+                recycle_if_empty(&room);
+            }
+        }
+    });
+}
+
+pub async fn end_round_and_start_next(room: &Arc<Mutex<Room>>) {
+    let leaderboard = room.lock().unwrap().game_state.get_leaderboard();
+
+    broadcast_message_to_all(room, &ServerMessage::Leaderboard { entries: leaderboard }).await;
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    start_next_round(room).await;
+}
+
+pub async fn start_next_round(room: &Arc<Mutex<Room>>) {
+    room.lock().unwrap().game_state.reset_for_next_round();
+
+    let (current_round, max_rounds, rules, room_id) = {
+        let room = room.lock().unwrap();
+        (
+            room.game_state.current_round,
+            room.game_state.max_rounds,
+            room.game_state.mode.describe_rules(),
+            room.id.clone(),
+        )
+    };
+
+    broadcast_message_to_all(room, &ServerMessage::RoundStart { round: current_round, max_rounds, rules }).await;
+
+    println!("Room {}: round {} started with existing players!", room_id, current_round);
+}
+
+pub async fn end_game(state: &AppState, room: &Arc<Mutex<Room>>) {
+    let standings = room.lock().unwrap().game_state.get_leaderboard();
+
+    broadcast_message_to_all(room, &ServerMessage::GameOver { standings }).await;
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let (active_players, players, room_id) = {
+        let room = room.lock().unwrap();
+        (
+            room.game_state.active_connections.keys().cloned().collect::<Vec<_>>(),
+            room.game_state.players.clone(),
+            room.id.clone(),
+        )
+    };
+
+    for player_id in active_players {
+        if let Some(player) = players.get(&player_id) {
+            player.sender.close();
+        }
+    }
+
+    println!("Room {}: game completed! Final results sent to all players.", room_id);
+
+    state.lobby.lock().unwrap().remove_room(&room_id);
+
+    println!("Room {} recycled. Ready for a new game.", room_id);
+}
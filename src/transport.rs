@@ -0,0 +1,74 @@
+use axum::extract::ws::Message as WsMessage;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+use crate::protocol::ServerMessage;
+
+/// Capacity of a player's outbound channel. A client that can't keep up and
+/// fills this buffer is disconnected rather than left to grow an unbounded
+/// backlog of stale messages.
+pub const PLAYER_CHANNEL_CAPACITY: usize = 200;
+
+/// Result of attempting to hand a message to a player's outbound channel.
+pub enum SendOutcome {
+    Sent,
+    /// The channel is full; the caller should treat this player as fallen
+    /// too far behind and disconnect them.
+    Full,
+    /// The write task for this player has already gone away.
+    Closed,
+}
+
+/// Wraps the two kinds of per-player output channel the server supports, so
+/// game logic can stay transport-agnostic and just call `send_message`/`close`.
+#[derive(Clone, Debug)]
+pub enum PlayerTransport {
+    Ws(Sender<WsMessage>),
+    Tcp(Sender<String>),
+}
+
+impl PlayerTransport {
+    /// WS clients get the versioned `serde_json` frame; plain-TCP/netcat
+    /// clients get the old free-form plaintext rendering instead.
+    pub fn send_message(&self, msg: &ServerMessage) -> SendOutcome {
+        match self {
+            PlayerTransport::Ws(tx) => {
+                let Ok(json) = serde_json::to_string(msg) else {
+                    return SendOutcome::Closed;
+                };
+                Self::outcome(tx.try_send(WsMessage::Text(json)))
+            }
+            PlayerTransport::Tcp(tx) => Self::outcome(tx.try_send(msg.to_plaintext())),
+        }
+    }
+
+    /// Sends a WS ping frame as part of the heartbeat sweep. A no-op for
+    /// plain-TCP connections, which have no ping/pong framing.
+    pub fn ping(&self) -> SendOutcome {
+        match self {
+            PlayerTransport::Ws(tx) => Self::outcome(tx.try_send(WsMessage::Ping(Vec::new()))),
+            PlayerTransport::Tcp(_) => SendOutcome::Sent,
+        }
+    }
+
+    pub fn close(&self) {
+        match self {
+            PlayerTransport::Ws(tx) => {
+                let _ = tx.try_send(WsMessage::Close(None));
+            }
+            PlayerTransport::Tcp(_) => {
+                // Plain TCP has no close frame, so this is a no-op: the
+                // connection stays open until every sender for it is
+                // dropped or the client hangs up.
+            }
+        }
+    }
+
+    fn outcome<T>(result: Result<(), TrySendError<T>>) -> SendOutcome {
+        match result {
+            Ok(()) => SendOutcome::Sent,
+            Err(TrySendError::Full(_)) => SendOutcome::Full,
+            Err(TrySendError::Closed(_)) => SendOutcome::Closed,
+        }
+    }
+}